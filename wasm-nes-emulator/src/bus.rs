@@ -0,0 +1,114 @@
+// Memory access is routed through a `Bus` so the CPU core never touches a raw
+// array directly. This lets hosts model hardware registers, read-only ROM
+// windows, and bank-switched address spaces without changing the CPU.
+
+/// Anything the CPU can read from and write to over its 16-bit address space.
+pub trait Bus {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, data: u8);
+}
+
+/// A handler mapped over a range of addresses. Returning `None` from `read`
+/// (or `false` from `write`) passes the access on to the next peripheral mapped
+/// over the address and finally to the backing RAM, so a peripheral
+/// can intercept only the addresses it cares about.
+pub trait Peripheral {
+    fn read(&mut self, addr: u16) -> Option<u8>;
+    fn write(&mut self, addr: u16, data: u8) -> bool;
+}
+
+struct Handler {
+    start: u16,
+    end: u16,
+    peripheral: Box<dyn Peripheral>,
+}
+
+/// The default bus: a flat RAM backing store with optional peripherals mapped
+/// over address ranges. Peripherals are consulted in registration order before
+/// RAM, so the first one whose range covers an address and that services the
+/// access wins.
+pub struct Ram {
+    memory: [u8; 0x10000],
+    peripherals: Vec<Handler>,
+}
+
+impl Ram {
+    pub fn new() -> Self {
+        Ram {
+            memory: [0; 0x10000],
+            peripherals: Vec::new(),
+        }
+    }
+
+    /// Map a peripheral over `start..=end`. Writes outside any range, and reads
+    /// a peripheral declines to service, fall through to RAM.
+    pub fn register(&mut self, start: u16, end: u16, peripheral: Box<dyn Peripheral>) {
+        self.peripherals.push(Handler {
+            start,
+            end,
+            peripheral,
+        });
+    }
+}
+
+impl Bus for Ram {
+    fn read(&mut self, addr: u16) -> u8 {
+        for handler in self.peripherals.iter_mut() {
+            if addr >= handler.start && addr <= handler.end {
+                if let Some(data) = handler.peripheral.read(addr) {
+                    return data;
+                }
+            }
+        }
+        self.memory[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        for handler in self.peripherals.iter_mut() {
+            if addr >= handler.start && addr <= handler.end {
+                if handler.peripheral.write(addr, data) {
+                    return;
+                }
+            }
+        }
+        self.memory[addr as usize] = data;
+    }
+}
+
+/// A peripheral that backs a high address window with several swappable
+/// buffers, enough to model Apple-II language-card layouts or NES mappers. The
+/// host calls `select` to bring a bank into the window.
+pub struct BankSwitch {
+    base: u16,
+    banks: Vec<Vec<u8>>,
+    active: usize,
+}
+
+impl BankSwitch {
+    pub fn new(base: u16, banks: Vec<Vec<u8>>) -> Self {
+        BankSwitch {
+            base,
+            banks,
+            active: 0,
+        }
+    }
+
+    pub fn select(&mut self, bank: usize) {
+        self.active = bank;
+    }
+}
+
+impl Peripheral for BankSwitch {
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        let offset = (addr - self.base) as usize;
+        self.banks[self.active].get(offset).copied()
+    }
+
+    fn write(&mut self, addr: u16, data: u8) -> bool {
+        let offset = (addr - self.base) as usize;
+        if let Some(slot) = self.banks[self.active].get_mut(offset) {
+            *slot = data;
+        }
+        true
+    }
+}
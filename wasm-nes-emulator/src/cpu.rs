@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use crate::opcodes;
+use crate::bus::{Bus, Ram};
 
 #[derive(Debug)]
 #[allow(non_camel_case_types)]
@@ -11,8 +12,10 @@ pub enum AddressingMode {
    Absolute,
    Absolute_X,
    Absolute_Y,
+   Indirect,
    Indirect_X,
    Indirect_Y,
+   Relative,
    NoneAddressing,
 }
 
@@ -24,27 +27,74 @@ pub struct CPU {
     //NV1BDIZC
     pub status: u8,
     pub program_counter: u16,
-    memory: [u8; 0xFFFF]
+    pub stack_pointer: u8,
+    pub cycles: u64,
+    halt: bool,
+    nmi: bool,
+    irq: bool,
+    trace_enabled: bool,
+    page_crossed: bool,
+    branch_cycles: u8,
+    bus: Box<dyn Bus>,
 }
 
+const STACK: u16 = 0x0100;
+const STACK_RESET: u8 = 0xFD;
+
+// Base cycle count for every opcode, indexed by the opcode byte. Illegal
+// opcodes carry their usual counts except the JAM opcodes, which are left at
+// 0; none of them dispatch. Page-crossing and
+// taken-branch penalties are added on top of these base counts in `step`.
+#[rustfmt::skip]
+const CYCLES: [u8; 256] = [
+    7, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+    2, 6, 0, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5,
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+    2, 5, 0, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4,
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+];
+
 impl CPU {
     pub fn new() -> Self {
+        CPU::new_with_bus(Box::new(Ram::new()))
+    }
+
+    pub fn new_with_bus(bus: Box<dyn Bus>) -> Self {
         CPU {
             register_a: 0,
             register_x: 0,
             register_y: 0,
             status: 0,
             program_counter: 0,
-            memory: [0; 0xFFFF],
+            stack_pointer: STACK_RESET,
+            cycles: 0,
+            halt: false,
+            nmi: false,
+            irq: false,
+            trace_enabled: false,
+            page_crossed: false,
+            branch_cycles: 0,
+            bus,
         }
     }
-    
-    pub fn mem_read(&self, addr: u16) -> u8 {
-        self.memory[addr as usize]
+
+    pub fn mem_read(&mut self, addr: u16) -> u8 {
+        self.bus.read(addr)
     }
-    
+
     pub fn mem_write(&mut self, addr: u16, data: u8) {
-        self.memory[addr as usize] = data;
+        self.bus.write(addr, data);
     }
     
     fn mem_read_u16(&mut self, pos: u16) -> u16 {
@@ -59,40 +109,95 @@ impl CPU {
         self.mem_write(pos, lo);
         self.mem_write(pos + 1, hi);
     }
-    
-    fn get_operand_address(&mut self, mode: &AddressingMode) -> u16 {
 
+    fn stack_push(&mut self, data: u8) {
+        self.mem_write(STACK + self.stack_pointer as u16, data);
+        self.stack_pointer = self.stack_pointer.wrapping_sub(1);
+    }
+
+    fn stack_pop(&mut self) -> u8 {
+        self.stack_pointer = self.stack_pointer.wrapping_add(1);
+        self.mem_read(STACK + self.stack_pointer as u16)
+    }
+
+    fn stack_push_u16(&mut self, data: u16) {
+        let hi = (data >> 8) as u8;
+        let lo = (data & 0xff) as u8;
+        self.stack_push(hi);
+        self.stack_push(lo);
+    }
+
+    fn stack_pop_u16(&mut self) -> u16 {
+        let lo = self.stack_pop() as u16;
+        let hi = self.stack_pop() as u16;
+        (hi << 8) | lo
+    }
+
+    fn get_operand_address(&mut self, mode: &AddressingMode) -> u16 {
        match mode {
            AddressingMode::Immediate => self.program_counter,
+           _ => self.get_absolute_address(mode, self.program_counter),
+       }
+    }
+
+    // Resolve the effective address for `mode` given that the operand bytes
+    // start at `addr`. Split out from `get_operand_address` so the trace logger
+    // can decode an instruction without disturbing the program counter.
+    fn get_absolute_address(&mut self, mode: &AddressingMode, addr: u16) -> u16 {
+
+       match mode {
+           AddressingMode::Immediate => addr,
+
+           AddressingMode::ZeroPage  => self.mem_read(addr) as u16,
+
+           AddressingMode::Absolute => self.mem_read_u16(addr),
 
-           AddressingMode::ZeroPage  => self.mem_read(self.program_counter) as u16,
-          
-           AddressingMode::Absolute => self.mem_read_u16(self.program_counter),
-        
            AddressingMode::ZeroPage_X => {
-               let pos = self.mem_read(self.program_counter);
+               let pos = self.mem_read(addr);
                let addr = pos.wrapping_add(self.register_x) as u16;
                addr
            }
            AddressingMode::ZeroPage_Y => {
-               let pos = self.mem_read(self.program_counter);
+               let pos = self.mem_read(addr);
                let addr = pos.wrapping_add(self.register_y) as u16;
                addr
            }
 
            AddressingMode::Absolute_X => {
-               let base = self.mem_read_u16(self.program_counter);
+               let base = self.mem_read_u16(addr);
                let addr = base.wrapping_add(self.register_x as u16);
+               self.page_crossed = base & 0xFF00 != addr & 0xFF00;
                addr
            }
            AddressingMode::Absolute_Y => {
-               let base = self.mem_read_u16(self.program_counter);
+               let base = self.mem_read_u16(addr);
                let addr = base.wrapping_add(self.register_y as u16);
+               self.page_crossed = base & 0xFF00 != addr & 0xFF00;
                addr
            }
 
+           AddressingMode::Indirect => {
+               let ptr = self.mem_read_u16(addr);
+               // Reproduce the 6502 page-boundary bug: an indirect vector whose
+               // low byte sits at $xxFF reads the high byte from $xx00, not the
+               // next page.
+               if ptr & 0x00FF == 0x00FF {
+                   let lo = self.mem_read(ptr);
+                   let hi = self.mem_read(ptr & 0xFF00);
+                   (hi as u16) << 8 | (lo as u16)
+               } else {
+                   self.mem_read_u16(ptr)
+               }
+           }
+
+           AddressingMode::Relative => {
+               let offset = self.mem_read(addr) as i8;
+               let base = addr.wrapping_add(1);
+               base.wrapping_add(offset as u16)
+           }
+
            AddressingMode::Indirect_X => {
-               let base = self.mem_read(self.program_counter);
+               let base = self.mem_read(addr);
 
                let ptr: u8 = (base as u8).wrapping_add(self.register_x);
                let lo = self.mem_read(ptr as u16);
@@ -100,15 +205,16 @@ impl CPU {
                (hi as u16) << 8 | (lo as u16)
            }
            AddressingMode::Indirect_Y => {
-               let base = self.mem_read(self.program_counter);
+               let base = self.mem_read(addr);
 
                let lo = self.mem_read(base as u16);
                let hi = self.mem_read((base as u8).wrapping_add(1) as u16);
                let deref_base = (hi as u16) << 8 | (lo as u16);
                let deref = deref_base.wrapping_add(self.register_y as u16);
+               self.page_crossed = deref_base & 0xFF00 != deref & 0xFF00;
                deref
            }
-         
+
            AddressingMode::NoneAddressing => {
                panic!("mode {:?} is not supported", mode);
            }
@@ -116,7 +222,9 @@ impl CPU {
     }
     
     pub fn load(&mut self, program: Vec<u8>) {
-        self.memory[0x8000 .. (0x8000 + program.len())].copy_from_slice(&program[..]);
+        for (i, byte) in program.iter().enumerate() {
+            self.mem_write(0x8000 + i as u16, *byte);
+        }
         self.mem_write_u16(0xFFFC, 0x8000);
     }
     
@@ -125,9 +233,48 @@ impl CPU {
         self.register_x = 0;
         self.register_y = 0;
         self.status = 0;
+        self.stack_pointer = STACK_RESET;
+        self.cycles = 0;
+        self.halt = false;
+        self.nmi = false;
+        self.irq = false;
         self.program_counter = self.mem_read_u16(0xFFFC);
     }
     
+    /// Toggle nestest-style execution tracing at runtime. When enabled, a
+    /// formatted line is emitted on stdout before each instruction executes.
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.trace_enabled = enabled;
+    }
+
+    /// Assert the non-maskable interrupt line. Serviced before the next
+    /// instruction regardless of the I flag.
+    pub fn trigger_nmi(&mut self) {
+        self.nmi = true;
+    }
+
+    /// Assert the maskable interrupt line. Serviced before the next instruction
+    /// only while the I flag is clear.
+    pub fn trigger_irq(&mut self) {
+        self.irq = true;
+    }
+
+    // Push PC and the status byte, raise the I flag, and jump through `vector`.
+    // `set_break` controls the B bit in the pushed status (set for BRK, clear
+    // for hardware interrupts).
+    fn interrupt(&mut self, vector: u16, set_break: bool) {
+        self.stack_push_u16(self.program_counter);
+        let flag = if set_break {
+            self.status | 0b0011_0000
+        } else {
+            (self.status & 0b1110_1111) | 0b0010_0000
+        };
+        self.stack_push(flag);
+        self.status = self.status | 0b0000_0100;
+        self.program_counter = self.mem_read_u16(vector);
+        self.cycles += 7;
+    }
+
     pub fn load_and_run(&mut self, program: Vec<u8>) {
        self.load(program);
        self.reset();
@@ -143,17 +290,41 @@ impl CPU {
 
 impl CPU {
 
-    pub fn run(&mut self) {        
+    pub fn run(&mut self) {
+        while !self.halt {
+            self.step();
+        }
+    }
+
+    /// Execute a single instruction and return the number of cycles it
+    /// consumed, so callers can throttle execution to a target clock rate.
+    pub fn step(&mut self) -> u8 {
     let ref opcodes: HashMap<u8, &'static opcodes::OpCode> = *opcodes::OPSCODES_MAP;
-    
-        loop {
+
+            // Service pending interrupts before fetching the next opcode. NMI is
+            // always honored; IRQ is masked while the I flag is set.
+            if self.nmi {
+                self.nmi = false;
+                self.interrupt(0xFFFA, false);
+            } else if self.irq && self.status & 0b0000_0100 == 0 {
+                self.irq = false;
+                self.interrupt(0xFFFE, false);
+            }
+
+            if self.trace_enabled {
+                println!("{}", self.trace());
+            }
+
+            self.page_crossed = false;
+            self.branch_cycles = 0;
+
             //let opscode = self.mem_read(self.program_counter);
             let code = self.mem_read(self.program_counter);
             self.program_counter += 1;
             let program_counter_state = self.program_counter;
-            
+
             let opcode = opcodes.get(&code).expect(&format!("Code: {:x} not found", code));
-            
+
             match code {
                 0x85 | 0x95 | 0x8D | 0x9D | 0x99 | 0x81 | 0x91 => self.write_reg(&opcode.address_mode, self.register_a),
                 0x86 | 0x96 | 0x8E => self.write_reg(&opcode.address_mode, self.register_x),
@@ -174,28 +345,305 @@ impl CPU {
                 0x09 | 0x05 | 0x15 | 0x0D | 0x1D | 0x19 | 0x01 | 0x11 => self.ora(&opcode.address_mode),
                 0x69 | 0x65 | 0x75 | 0x6D | 0x7D | 0x79 | 0x61 | 0x71 => self.adc(&opcode.address_mode),
                 0xE9 | 0xE5 | 0xF5 | 0xED | 0xFD | 0xF9 | 0xE1 | 0xF1 => self.sbc(&opcode.address_mode),
+                0x48 => self.pha(),
+                0x08 => self.php(),
+                0x68 => self.pla(),
+                0x28 => self.plp(),
+                0x20 => self.jsr(),
+                0x60 => self.rts(),
+                0x40 => self.rti(),
+                0x4C => self.program_counter = self.get_operand_address(&AddressingMode::Absolute),
+                0x6C => self.program_counter = self.get_operand_address(&AddressingMode::Indirect),
+                0x90 => self.branch(self.status & 0b0000_0001 == 0),
+                0xB0 => self.branch(self.status & 0b0000_0001 != 0),
+                0xD0 => self.branch(self.status & 0b0000_0010 == 0),
+                0xF0 => self.branch(self.status & 0b0000_0010 != 0),
+                0x10 => self.branch(self.status & 0b1000_0000 == 0),
+                0x30 => self.branch(self.status & 0b1000_0000 != 0),
+                0x50 => self.branch(self.status & 0b0100_0000 == 0),
+                0x70 => self.branch(self.status & 0b0100_0000 != 0),
+                0x0A => self.asl_accumulator(),
+                0x06 | 0x16 | 0x0E | 0x1E => self.asl(&opcode.address_mode),
+                0x4A => self.lsr_accumulator(),
+                0x46 | 0x56 | 0x4E | 0x5E => self.lsr(&opcode.address_mode),
+                0x2A => self.rol_accumulator(),
+                0x26 | 0x36 | 0x2E | 0x3E => self.rol(&opcode.address_mode),
+                0x6A => self.ror_accumulator(),
+                0x66 | 0x76 | 0x6E | 0x7E => self.ror(&opcode.address_mode),
+                0xE6 | 0xF6 | 0xEE | 0xFE => self.inc(&opcode.address_mode),
+                0xC6 | 0xD6 | 0xCE | 0xDE => self.dec(&opcode.address_mode),
+                0xC9 | 0xC5 | 0xD5 | 0xCD | 0xDD | 0xD9 | 0xC1 | 0xD1 => self.compare(&opcode.address_mode, self.register_a),
+                0xE0 | 0xE4 | 0xEC => self.compare(&opcode.address_mode, self.register_x),
+                0xC0 | 0xC4 | 0xCC => self.compare(&opcode.address_mode, self.register_y),
+                0xF8 => self.status = self.status | 0b0000_1000,
+                0xD8 => self.status = self.status & 0b1111_0111,
+                0x78 => self.status = self.status | 0b0000_0100,
+                0x58 => self.status = self.status & 0b1111_1011,
                 0xEA => (),
                 0x00 => {
-                    return
+                    // BRK doubles as the emulator's stop signal. Record the
+                    // machine state on the stack (B set, PC past the padding
+                    // byte) as the hardware would before halting.
+                    self.stack_push_u16(self.program_counter + 1);
+                    self.stack_push(self.status | 0b0011_0000);
+                    self.status = self.status | 0b0000_0100;
+                    self.halt = true;
                 }
                 _ => todo!()
             }
-            
-            
+
             if program_counter_state == self.program_counter {
                 self.program_counter += (opcode.bytes - 1) as u16;
             }
-            
-        //println!("pc: {}, a: {}, x: {}, y: {}, op: {:#04x}", self.program_counter, self.register_a, self.register_x, self.register_y, opscode);
+
+            let mut spent = CYCLES[code as usize] as u64;
+            if self.page_crossed {
+                spent += 1;
+            }
+            spent += self.branch_cycles as u64;
+            self.cycles += spent;
+            spent as u8
+    }
+
+    /// Build a nestest-style trace line for the instruction at the program
+    /// counter: address, raw opcode bytes, decoded mnemonic/operand, and a
+    /// snapshot of the registers. The format is stable so a run can be diffed
+    /// against a known-good reference log.
+    pub fn trace(&mut self) -> String {
+        let ref opcodes: HashMap<u8, &'static opcodes::OpCode> = *opcodes::OPSCODES_MAP;
+
+        let begin = self.program_counter;
+        let code = self.mem_read(begin);
+        let opcode = opcodes.get(&code).expect(&format!("Code: {:x} not found", code));
+
+        let (mem_addr, stored_value) = match opcode.address_mode {
+            AddressingMode::Immediate | AddressingMode::NoneAddressing | AddressingMode::Relative => (0, 0),
+            _ => {
+                let addr = self.get_absolute_address(&opcode.address_mode, begin + 1);
+                (addr, self.mem_read(addr))
+            }
+        };
+
+        let mut hex_dump = vec![code];
+
+        let operand = match opcode.bytes {
+            1 => match code {
+                0x0A | 0x4A | 0x2A | 0x6A => String::from("A"),
+                _ => String::from(""),
+            },
+            2 => {
+                let address = self.mem_read(begin + 1);
+                hex_dump.push(address);
+                match opcode.address_mode {
+                    AddressingMode::Immediate => format!("#${:02x}", address),
+                    AddressingMode::ZeroPage => format!("${:02x} = {:02x}", mem_addr, stored_value),
+                    AddressingMode::ZeroPage_X => format!("${:02x},X @ {:02x} = {:02x}", address, mem_addr, stored_value),
+                    AddressingMode::ZeroPage_Y => format!("${:02x},Y @ {:02x} = {:02x}", address, mem_addr, stored_value),
+                    AddressingMode::Indirect_X => format!("(${:02x},X) @ {:02x} = {:04x} = {:02x}", address, address.wrapping_add(self.register_x), mem_addr, stored_value),
+                    AddressingMode::Indirect_Y => format!("(${:02x}),Y = {:04x} @ {:04x} = {:02x}", address, mem_addr.wrapping_sub(self.register_y as u16), mem_addr, stored_value),
+                    AddressingMode::Relative => {
+                        let jump = (begin as usize + 2).wrapping_add((address as i8) as usize);
+                        format!("${:04x}", jump as u16)
+                    }
+                    _ => format!("${:02x}", address),
+                }
+            }
+            3 => {
+                let lo = self.mem_read(begin + 1);
+                let hi = self.mem_read(begin + 2);
+                hex_dump.push(lo);
+                hex_dump.push(hi);
+                let address = self.mem_read_u16(begin + 1);
+                match opcode.address_mode {
+                    AddressingMode::Absolute => format!("${:04x} = {:02x}", mem_addr, stored_value),
+                    AddressingMode::Absolute_X => format!("${:04x},X @ {:04x} = {:02x}", address, mem_addr, stored_value),
+                    AddressingMode::Absolute_Y => format!("${:04x},Y @ {:04x} = {:02x}", address, mem_addr, stored_value),
+                    _ => format!("${:04x}", address),
+                }
+            }
+            _ => String::from(""),
+        };
+
+        let hex_str = hex_dump
+            .iter()
+            .map(|z| format!("{:02x}", z))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let asm_str = format!("{:04x}  {:8} {: >4} {}", begin, hex_str, opcode.mnemonic, operand)
+            .trim()
+            .to_string();
+
+        format!(
+            "{:47} A:{:02x} X:{:02x} Y:{:02x} P:{:02x} SP:{:02x}",
+            asm_str, self.register_a, self.register_x, self.register_y, self.status, self.stack_pointer
+        )
+        .to_ascii_uppercase()
+    }
+
+    fn set_carry(&mut self, condition: bool) {
+        if condition {
+            self.status = self.status | 0b0000_0001;
+        } else {
+            self.status = self.status & 0b1111_1110;
         }
     }
-    
+
+    fn asl_accumulator(&mut self) {
+        let mut data = self.register_a;
+        self.set_carry(data >> 7 == 1);
+        data = data << 1;
+        self.register_a = data;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn asl(&mut self, mode: &AddressingMode) {
+        let addr = self.get_operand_address(mode);
+        let mut data = self.mem_read(addr);
+        self.set_carry(data >> 7 == 1);
+        data = data << 1;
+        self.mem_write(addr, data);
+        self.page_crossed = false;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn lsr_accumulator(&mut self) {
+        let mut data = self.register_a;
+        self.set_carry(data & 1 == 1);
+        data = data >> 1;
+        self.register_a = data;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn lsr(&mut self, mode: &AddressingMode) {
+        let addr = self.get_operand_address(mode);
+        let mut data = self.mem_read(addr);
+        self.set_carry(data & 1 == 1);
+        data = data >> 1;
+        self.mem_write(addr, data);
+        self.page_crossed = false;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn rol_accumulator(&mut self) {
+        let mut data = self.register_a;
+        let carry_in = self.status & 0b0000_0001;
+        self.set_carry(data >> 7 == 1);
+        data = (data << 1) | carry_in;
+        self.register_a = data;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn rol(&mut self, mode: &AddressingMode) {
+        let addr = self.get_operand_address(mode);
+        let mut data = self.mem_read(addr);
+        let carry_in = self.status & 0b0000_0001;
+        self.set_carry(data >> 7 == 1);
+        data = (data << 1) | carry_in;
+        self.mem_write(addr, data);
+        self.page_crossed = false;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn ror_accumulator(&mut self) {
+        let mut data = self.register_a;
+        let carry_in = self.status & 0b0000_0001;
+        self.set_carry(data & 1 == 1);
+        data = (data >> 1) | (carry_in << 7);
+        self.register_a = data;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn ror(&mut self, mode: &AddressingMode) {
+        let addr = self.get_operand_address(mode);
+        let mut data = self.mem_read(addr);
+        let carry_in = self.status & 0b0000_0001;
+        self.set_carry(data & 1 == 1);
+        data = (data >> 1) | (carry_in << 7);
+        self.mem_write(addr, data);
+        self.page_crossed = false;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn inc(&mut self, mode: &AddressingMode) {
+        let addr = self.get_operand_address(mode);
+        let data = self.mem_read(addr).wrapping_add(1);
+        self.mem_write(addr, data);
+        self.page_crossed = false;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn dec(&mut self, mode: &AddressingMode) {
+        let addr = self.get_operand_address(mode);
+        let data = self.mem_read(addr).wrapping_sub(1);
+        self.mem_write(addr, data);
+        self.page_crossed = false;
+        self.update_zero_and_negative_flags(data);
+    }
+
+    fn branch(&mut self, condition: bool) {
+        if condition {
+            let base = self.program_counter.wrapping_add(1);
+            let jump_addr = self.get_operand_address(&AddressingMode::Relative);
+            self.branch_cycles = if base & 0xFF00 != jump_addr & 0xFF00 { 2 } else { 1 };
+            self.program_counter = jump_addr;
+        }
+    }
+
+    fn compare(&mut self, mode: &AddressingMode, reg: u8) {
+        let value = self.get_value(mode);
+        if reg >= value {
+            self.status = self.status | 0b0000_0001;
+        } else {
+            self.status = self.status & 0b1111_1110;
+        }
+        self.update_zero_and_negative_flags(reg.wrapping_sub(value));
+    }
+
+    fn pha(&mut self) {
+        self.stack_push(self.register_a);
+    }
+
+    fn php(&mut self) {
+        self.stack_push(self.status | 0b0011_0000);
+    }
+
+    fn pla(&mut self) {
+        self.register_a = self.stack_pop();
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    fn plp(&mut self) {
+        self.status = self.stack_pop();
+    }
+
+    fn jsr(&mut self) {
+        self.stack_push_u16(self.program_counter + 2 - 1);
+        let target = self.mem_read_u16(self.program_counter);
+        self.program_counter = target;
+    }
+
+    fn rts(&mut self) {
+        self.program_counter = self.stack_pop_u16() + 1;
+    }
+
+    fn rti(&mut self) {
+        self.status = self.stack_pop();
+        self.program_counter = self.stack_pop_u16();
+    }
+
     fn write_reg(&mut self, mode: &AddressingMode, reg: u8) {
         let addr = self.get_operand_address(mode);
         self.mem_write(addr, reg);
+        // Stores always pay the fixed store cost; the page-cross penalty only
+        // applies to read-style indexed access.
+        self.page_crossed = false;
     }
     
     fn add_to_reg_a(&mut self, value: u8) {
+        if self.status & 0b0000_1000 != 0 {
+            self.add_to_reg_a_decimal(value);
+            return;
+        }
         let mut sum: u16 = (self.register_a as u16) + (value as u16);
         if sum > 0xFF {
             sum = sum - 256;
@@ -212,14 +660,70 @@ impl CPU {
         self.update_zero_and_negative_flags(self.register_a);
     }
     
+    // Packed BCD addition used when the D flag is set: add the low nibbles plus
+    // the carry-in and fix up by 6 if they exceed 9, then fold in the high
+    // nibbles and add 0x60 (setting carry) if the result leaves the decimal
+    // range.
+    fn add_to_reg_a_decimal(&mut self, value: u8) {
+        let carry_in = (self.status & 0b0000_0001) as u16;
+        let a = self.register_a as u16;
+        let v = value as u16;
+
+        let mut lo = (a & 0x0F) + (v & 0x0F) + carry_in;
+        if lo > 0x09 {
+            lo += 0x06;
+        }
+        let mut sum = (a & 0xF0) + (v & 0xF0) + (lo & 0x0F) + (lo & 0x10);
+        if sum > 0x9F {
+            sum += 0x60;
+            self.status = self.status | 0b0000_0001;
+        } else {
+            self.status = self.status & 0b1111_1110;
+        }
+        self.register_a = sum as u8;
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
+    // Symmetric packed BCD subtraction: subtract the nibbles with borrow and
+    // correct any nibble that borrowed by 6. The carry flag reflects the binary
+    // borrow (set = no borrow), matching the hardware.
+    fn sub_from_reg_a_decimal(&mut self, value: u8) {
+        let borrow = 1 - (self.status & 0b0000_0001) as i16;
+        let a = self.register_a as i16;
+        let v = value as i16;
+
+        let binary = a - v - borrow;
+
+        let mut lo = (a & 0x0F) - (v & 0x0F) - borrow;
+        if lo < 0 {
+            lo -= 0x06;
+        }
+        let mut hi = (a >> 4) - (v >> 4) - if lo < 0 { 1 } else { 0 };
+        if hi < 0 {
+            hi -= 0x06;
+        }
+
+        if binary >= 0 {
+            self.status = self.status | 0b0000_0001;
+        } else {
+            self.status = self.status & 0b1111_1110;
+        }
+        self.register_a = (hi as u8).wrapping_shl(4) | ((lo as u8) & 0x0F);
+        self.update_zero_and_negative_flags(self.register_a);
+    }
+
     fn adc(&mut self, mode: &AddressingMode) {
         let value = self.get_value(mode);
         self.add_to_reg_a(value);
     }
-    
+
     fn sbc(&mut self, mode: &AddressingMode) {
         let value = self.get_value(mode);
-        self.add_to_reg_a((value as i8).wrapping_neg().wrapping_sub(1) as u8);
+        if self.status & 0b0000_1000 != 0 {
+            self.sub_from_reg_a_decimal(value);
+        } else {
+            self.add_to_reg_a((value as i8).wrapping_neg().wrapping_sub(1) as u8);
+        }
     }
         
     fn lda(&mut self, mode: &AddressingMode) {